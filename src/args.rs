@@ -8,4 +8,18 @@ pub struct Args {
     pub port: u16,
     #[arg(short, long)]
     pub root: Option<PathBuf>,
+    /// Path to a PEM-encoded certificate chain. Requires `--tls-key` to enable HTTPS.
+    #[arg(long)]
+    pub tls_cert: Option<PathBuf>,
+    /// Path to the PEM-encoded private key matching `--tls-cert`.
+    #[arg(long)]
+    pub tls_key: Option<PathBuf>,
+    /// Recover the real client address from a PROXY protocol v1/v2 header, for use
+    /// behind a TCP load balancer or tunnel.
+    #[arg(long)]
+    pub proxy_protocol: bool,
+    /// Maximum number of connections handled concurrently; further connections wait
+    /// for a slot to free up instead of being accepted unconditionally.
+    #[arg(long, default_value_t = 1024)]
+    pub max_connections: usize,
 }
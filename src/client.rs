@@ -1,17 +1,57 @@
-use tokio::io::{AsyncBufRead, AsyncWrite};
+use std::net::SocketAddr;
 
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+
+use crate::proxy;
 use crate::req::{parse_request, Request};
+use crate::resp::Response;
+use crate::ws;
 
-pub struct Connection<S: AsyncBufRead + AsyncWrite + Unpin> {
-    pub stream: S,
+/// A single client connection, generic over the underlying transport so the same
+/// request/response path works over plain TCP and TLS alike.
+pub struct Connection<S> {
+    stream: BufStream<S>,
 }
 
-impl<S: AsyncBufRead + AsyncWrite + Unpin> Connection<S> {
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
     pub fn new(stream: S) -> Self {
-        Self { stream }
+        Self {
+            stream: BufStream::new(stream),
+        }
     }
 
     pub async fn next_request(&mut self) -> anyhow::Result<Request> {
         parse_request(&mut self.stream).await
     }
+
+    /// Consumes a PROXY protocol header from the front of the connection and returns
+    /// the real client address it carries. `fallback_addr` is the address the
+    /// connection was actually accepted from, used for a v2 LOCAL header.
+    pub async fn read_proxy_header(
+        &mut self,
+        fallback_addr: SocketAddr,
+    ) -> anyhow::Result<SocketAddr> {
+        proxy::read_header(&mut self.stream, fallback_addr).await
+    }
+
+    pub async fn write_response(&mut self, response: Response) -> anyhow::Result<()> {
+        response.write(&mut self.stream).await
+    }
+
+    /// Completes the WebSocket handshake for `request` and hands the connection over
+    /// to the WebSocket echo loop for the rest of its lifetime.
+    pub async fn upgrade_to_websocket(&mut self, request: &Request) -> anyhow::Result<()> {
+        let key = request
+            .headers
+            .get("Sec-WebSocket-Key")
+            .ok_or_else(|| anyhow::anyhow!("missing Sec-WebSocket-Key"))?;
+
+        let bytes = ws::handshake_response(key)
+            .status_and_headers()
+            .into_bytes();
+        self.stream.write_all(&bytes).await?;
+        self.stream.flush().await?;
+
+        ws::echo(&mut self.stream).await
+    }
 }
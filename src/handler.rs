@@ -1,11 +1,19 @@
 use std::{env::current_dir, io, path::PathBuf};
 
-use crate::req::Request;
-use crate::resp::{Response, Status};
+use crate::mjpeg::FrameBroadcaster;
+use crate::req::{Method, Request};
+use crate::resp::{self, Response, Status};
+
+/// Path at which the live MJPEG stream is served, routed ahead of the filesystem lookup.
+const MJPEG_STREAM_PATH: &str = "/stream.mjpg";
+
+/// Methods this handler supports, reported in the `Allow` header of `405` responses.
+const ALLOWED_METHODS: [Method; 2] = [Method::Get, Method::Head];
 
 #[derive(Debug, Clone)]
 pub struct StaticFileHandler {
     root: PathBuf,
+    mjpeg: FrameBroadcaster,
 }
 
 impl StaticFileHandler {
@@ -14,10 +22,36 @@ impl StaticFileHandler {
     }
 
     pub fn with_root(root: PathBuf) -> StaticFileHandler {
-        StaticFileHandler { root }
+        StaticFileHandler {
+            root,
+            mjpeg: FrameBroadcaster::new(),
+        }
+    }
+
+    /// The broadcaster backing `/stream.mjpg`, so a frame source can publish to it.
+    pub fn mjpeg_broadcaster(&self) -> &FrameBroadcaster {
+        &self.mjpeg
     }
 
     pub async fn handle(&self, request: Request) -> anyhow::Result<Response> {
+        if !matches!(request.method, Method::Get | Method::Head) {
+            return Ok(Response::method_not_allowed(&ALLOWED_METHODS));
+        }
+
+        let response = self.handle_get(&request).await?;
+
+        Ok(if request.method == Method::Head {
+            response.without_body()
+        } else {
+            response
+        })
+    }
+
+    async fn handle_get(&self, request: &Request) -> anyhow::Result<Response> {
+        if request.path == MJPEG_STREAM_PATH {
+            return Ok(Response::mjpeg_stream(self.mjpeg.subscribe()));
+        }
+
         let path = self.root.join(request.path.strip_prefix('/').unwrap());
 
         if !path.is_file() {
@@ -28,6 +62,15 @@ impl StaticFileHandler {
         }
 
         let file = tokio::fs::File::open(&path).await?;
-        Response::from_file(&path, file).await
+        let metadata = file.metadata().await?;
+
+        if resp::is_not_modified(&request.headers, &metadata)? {
+            return Response::not_modified(&metadata);
+        }
+
+        match request.headers.get("Range") {
+            Some(range) => Response::from_file_range(&path, file, &metadata, range).await,
+            None => Response::from_file(&path, file).await,
+        }
     }
 }
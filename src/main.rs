@@ -1,25 +1,30 @@
-use std::{net::SocketAddr, path::PathBuf};
+use std::{fs::File, io::BufReader, net::SocketAddr, sync::Arc, time::Duration};
 
 use clap::Parser;
 use tokio::{
-    io::{AsyncWrite, BufStream},
+    io::{AsyncRead, AsyncWrite},
     net::{TcpListener, TcpStream},
     signal,
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::JoinSet,
 };
+use tokio_rustls::{rustls, TlsAcceptor};
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+mod args;
+mod client;
 mod handler;
+mod mjpeg;
+mod proxy;
 mod req;
 mod resp;
+mod ws;
 
-#[derive(Parser, Debug)]
-pub struct Args {
-    #[arg(short, long, default_value_t = 8080)]
-    pub port: u16,
-    #[arg(short, long)]
-    pub root: Option<PathBuf>,
-}
+use args::Args;
+
+/// How long to wait for in-flight connections to finish on shutdown before aborting them.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -28,6 +33,8 @@ async fn main() -> anyhow::Result<()> {
 
     let args = Args::parse();
     let port = args.port;
+    let proxy_protocol = args.proxy_protocol;
+    let tls_acceptor = load_tls_acceptor(&args)?;
     let handler = args
         .root
         .map(handler::StaticFileHandler::with_root)
@@ -51,21 +58,46 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
-    let mut tasks = Vec::new();
+    let semaphore = Arc::new(Semaphore::new(args.max_connections));
+    let mut tasks = JoinSet::new();
 
     loop {
         let cancel_token = cancel_token.clone();
 
         tokio::select! {
-            Ok((stream, addr)) = listener.accept() => {
+            accepted = accept_with_permit(&listener, &semaphore) => {
+                let (permit, stream, addr) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        error!(?e, "failed to accept connection");
+                        continue;
+                    }
+                };
+
                 let handler = handler.clone();
-                let client_task = tokio::spawn(async move {
-                    if let Err(e) = handle_client(cancel_token, stream, addr, &handler).await {
+                let tls_acceptor = tls_acceptor.clone();
+                tasks.spawn(async move {
+                    let _permit = permit;
+                    let result = match tls_acceptor {
+                        Some(acceptor) => match acceptor.accept(stream).await {
+                            Ok(stream) => {
+                                handle_client(cancel_token, stream, addr, &handler, proxy_protocol).await
+                            }
+                            Err(e) => Err(e.into()),
+                        },
+                        None => handle_client(cancel_token, stream, addr, &handler, proxy_protocol).await,
+                    };
+
+                    if let Err(e) = result {
                         error!(?e, "failed to handle client");
                     }
                 });
-                tasks.push(client_task);
             },
+            Some(result) = tasks.join_next(), if !tasks.is_empty() => {
+                if let Err(e) = result {
+                    error!(?e, "client task panicked");
+                }
+            }
             _ = cancel_token.cancelled() => {
                 info!("stop listening");
                 break;
@@ -73,28 +105,90 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    futures::future::join_all(tasks).await;
+    info!(pending = tasks.len(), "draining in-flight connections");
+
+    let drained = tokio::time::timeout(DRAIN_TIMEOUT, async {
+        while let Some(result) = tasks.join_next().await {
+            if let Err(e) = result {
+                error!(?e, "client task panicked during drain");
+            }
+        }
+    })
+    .await;
+
+    if drained.is_err() {
+        warn!(
+            remaining = tasks.len(),
+            "drain timed out, aborting remaining connections"
+        );
+        tasks.shutdown().await;
+    }
 
     Ok(())
 }
 
-async fn handle_client(
+/// Waits for a connection slot to free up before accepting, so a flood of incoming
+/// connections queues in the kernel backlog instead of exhausting our own resources.
+async fn accept_with_permit(
+    listener: &TcpListener,
+    semaphore: &Arc<Semaphore>,
+) -> anyhow::Result<(OwnedSemaphorePermit, TcpStream, SocketAddr)> {
+    let permit = semaphore.clone().acquire_owned().await?;
+    let (stream, addr) = listener.accept().await?;
+
+    Ok((permit, stream, addr))
+}
+
+/// Builds a `TlsAcceptor` from `--tls-cert`/`--tls-key` when both are set, so the
+/// accept loop serves HTTPS instead of plain HTTP.
+fn load_tls_acceptor(args: &Args) -> anyhow::Result<Option<TlsAcceptor>> {
+    let (Some(cert_path), Some(key_path)) = (&args.tls_cert, &args.tls_key) else {
+        return Ok(None);
+    };
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert_path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key_path)?))?
+        .ok_or_else(|| anyhow::anyhow!("no private key found in {}", key_path.display()))?;
+
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(Some(TlsAcceptor::from(Arc::new(config))))
+}
+
+async fn handle_client<S: AsyncRead + AsyncWrite + Unpin>(
     cancel_token: CancellationToken,
-    stream: TcpStream,
+    stream: S,
     addr: SocketAddr,
     handler: &handler::StaticFileHandler,
+    proxy_protocol: bool,
 ) -> anyhow::Result<()> {
-    let mut stream = BufStream::new(stream);
+    let mut conn = client::Connection::new(stream);
+
+    let addr = if proxy_protocol {
+        conn.read_proxy_header(addr).await?
+    } else {
+        addr
+    };
 
     info!(?addr, "new connection");
 
     loop {
         tokio::select! {
-            req = req::parse_request(&mut stream) => {
+            req = conn.next_request() => {
                 match req {
                     Ok(req) => {
                         info!(?req, "incoming request");
-                        let close_conn = handle_req(req, &handler, &mut stream).await?;
+
+                        if ws::is_upgrade_request(&req) {
+                            conn.upgrade_to_websocket(&req).await?;
+                            break;
+                        }
+
+                        let close_conn = handle_req(req, handler, &mut conn).await?;
                         if close_conn {
                             break;
                         }
@@ -115,16 +209,16 @@ async fn handle_client(
     Ok(())
 }
 
-async fn handle_req<S: AsyncWrite + Unpin>(
+async fn handle_req<S: AsyncRead + AsyncWrite + Unpin>(
     req: req::Request,
     handler: &handler::StaticFileHandler,
-    stream: &mut S,
+    conn: &mut client::Connection<S>,
 ) -> anyhow::Result<bool> {
     let close_connection = req.headers.get("Connection") == Some(&"close".to_string());
 
     match handler.handle(req).await {
         Ok(resp) => {
-            resp.write(stream).await.unwrap();
+            conn.write_response(resp).await?;
         }
         Err(e) => {
             error!(?e, "failed to handle request");
@@ -0,0 +1,63 @@
+use bytes::Bytes;
+use futures::stream;
+use tokio::sync::broadcast;
+use tokio_util::io::StreamReader;
+
+/// Number of frames a slow subscriber can fall behind before it starts missing
+/// frames; a lagging subscriber skips ahead to the oldest frame still buffered.
+const CHANNEL_CAPACITY: usize = 4;
+
+/// Broadcasts JPEG frames from a single source (e.g. a camera) to any number of
+/// MJPEG clients, each reading at its own pace.
+#[derive(Debug, Clone)]
+pub struct FrameBroadcaster {
+    sender: broadcast::Sender<Bytes>,
+}
+
+impl FrameBroadcaster {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publishes a frame to all current subscribers. Dropped silently if there are none.
+    pub fn publish(&self, frame: Bytes) {
+        let _ = self.sender.send(frame);
+    }
+
+    /// Subscribes to the frame source, returning a reader of `multipart/x-mixed-replace`
+    /// parts ready to be streamed out as a `Response`.
+    pub fn subscribe(&self) -> impl tokio::io::AsyncRead + Unpin + Send + 'static {
+        let frames = stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(frame) => {
+                        return Some((Ok::<_, std::io::Error>(multipart_part(frame)), receiver))
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        StreamReader::new(frames)
+    }
+}
+
+impl Default for FrameBroadcaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn multipart_part(frame: Bytes) -> Bytes {
+    let mut part = format!(
+        "--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+        frame.len()
+    )
+    .into_bytes();
+    part.extend_from_slice(&frame);
+    part.extend_from_slice(b"\r\n");
+
+    Bytes::from(part)
+}
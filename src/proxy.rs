@@ -0,0 +1,129 @@
+use std::net::{IpAddr, SocketAddr};
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+const V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+/// Consumes a PROXY protocol header (v1 text or v2 binary) from the front of `stream`
+/// and returns the real client address it carries. `fallback_addr` (the address the
+/// socket was actually accepted from) is returned for a v2 LOCAL connection, which
+/// carries no client address of its own (e.g. a load balancer health check).
+pub async fn read_header<S: AsyncBufRead + Unpin>(
+    stream: &mut S,
+    fallback_addr: SocketAddr,
+) -> anyhow::Result<SocketAddr> {
+    // A header can legitimately arrive split across TCP segments, so wait until at
+    // least the v2 signature length is buffered (or the stream ends) before deciding
+    // which variant we're looking at. Nothing is consumed here: parse_v1/parse_v2 read
+    // the header themselves, so any request pipelined right after it stays buffered.
+    let buf = peek_at_least(stream, V2_SIGNATURE.len()).await?;
+
+    if buf.starts_with(&V2_SIGNATURE) {
+        parse_v2(stream, fallback_addr).await
+    } else if buf.starts_with(b"PROXY ") {
+        parse_v1(stream).await
+    } else {
+        Err(anyhow::anyhow!("no PROXY protocol header found"))
+    }
+}
+
+/// Waits until `stream`'s internal buffer holds at least `min_len` bytes (or the
+/// stream ends) and returns a copy of it, without consuming anything.
+async fn peek_at_least<S: AsyncBufRead + Unpin>(
+    stream: &mut S,
+    min_len: usize,
+) -> anyhow::Result<Vec<u8>> {
+    loop {
+        let buf = stream.fill_buf().await?;
+        if buf.len() >= min_len || buf.is_empty() {
+            return Ok(buf.to_vec());
+        }
+    }
+}
+
+async fn parse_v1<S: AsyncBufRead + Unpin>(stream: &mut S) -> anyhow::Result<SocketAddr> {
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+
+    let mut parts = line.trim_end().split_whitespace();
+
+    if parts.next() != Some("PROXY") {
+        return Err(anyhow::anyhow!("malformed PROXY protocol v1 header"));
+    }
+
+    match parts.next() {
+        Some("TCP4") | Some("TCP6") => {}
+        Some("UNKNOWN") => return Err(anyhow::anyhow!("PROXY protocol source is UNKNOWN")),
+        _ => return Err(anyhow::anyhow!("unsupported PROXY protocol family")),
+    }
+
+    let src_ip: IpAddr = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("missing PROXY source address"))?
+        .parse()?;
+    let _dst_ip: IpAddr = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("missing PROXY destination address"))?
+        .parse()?;
+    let src_port: u16 = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("missing PROXY source port"))?
+        .parse()?;
+
+    Ok(SocketAddr::new(src_ip, src_port))
+}
+
+async fn parse_v2<S: AsyncBufRead + Unpin>(
+    stream: &mut S,
+    fallback_addr: SocketAddr,
+) -> anyhow::Result<SocketAddr> {
+    let mut signature = [0u8; 12];
+    stream.read_exact(&mut signature).await?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+
+    let version = header[0] >> 4;
+    let command = header[0] & 0x0F;
+    let address_family = header[1] >> 4;
+    let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+
+    if version != 2 {
+        return Err(anyhow::anyhow!("unsupported PROXY protocol version"));
+    }
+
+    let mut address_block = vec![0u8; len];
+    stream.read_exact(&mut address_block).await?;
+
+    // LOCAL (command 0): the proxy's own connection (e.g. a health check), carrying
+    // no client address to recover — fall back to the address we actually accepted from.
+    if command == 0x0 {
+        return Ok(fallback_addr);
+    }
+
+    match address_family {
+        // AF_INET: 4-byte src addr, 4-byte dst addr, 2-byte src port, 2-byte dst port.
+        0x1 if address_block.len() >= 12 => {
+            let src_ip = IpAddr::from([
+                address_block[0],
+                address_block[1],
+                address_block[2],
+                address_block[3],
+            ]);
+            let src_port = u16::from_be_bytes([address_block[8], address_block[9]]);
+            Ok(SocketAddr::new(src_ip, src_port))
+        }
+        // AF_INET6: 16-byte src addr, 16-byte dst addr, 2-byte src port, 2-byte dst port.
+        0x2 if address_block.len() >= 36 => {
+            let mut src_ip = [0u8; 16];
+            src_ip.copy_from_slice(&address_block[0..16]);
+            let src_port = u16::from_be_bytes([address_block[32], address_block[33]]);
+            Ok(SocketAddr::new(IpAddr::from(src_ip), src_port))
+        }
+        _ => Err(anyhow::anyhow!(
+            "unsupported or truncated PROXY protocol v2 address block"
+        )),
+    }
+}
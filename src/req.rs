@@ -1,17 +1,39 @@
 use std::{collections::HashMap, hash::Hash};
 
-use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+/// Caps how much memory a single request body can claim via `Content-Length`.
+const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Request {
     pub method: Method,
     pub path: String,
     pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Method {
     Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Options,
+}
+
+impl Method {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Options => "OPTIONS",
+        }
+    }
 }
 
 impl TryFrom<&str> for Method {
@@ -20,6 +42,11 @@ impl TryFrom<&str> for Method {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
             "GET" => Ok(Method::Get),
+            "HEAD" => Ok(Method::Head),
+            "POST" => Ok(Method::Post),
+            "PUT" => Ok(Method::Put),
+            "DELETE" => Ok(Method::Delete),
+            "OPTIONS" => Ok(Method::Options),
             m => Err(anyhow::anyhow!("unsupported method: {m}")),
         }
     }
@@ -61,10 +88,25 @@ pub async fn parse_request(mut stream: impl AsyncBufRead + Unpin) -> anyhow::Res
         headers.insert(key.to_string(), value.to_string());
     }
 
+    let body = match headers.get("Content-Length") {
+        Some(len) => {
+            let len: u64 = len.parse()?;
+            if len > MAX_BODY_SIZE {
+                return Err(anyhow::anyhow!("request body too large: {len} bytes"));
+            }
+
+            let mut body = vec![0u8; len as usize];
+            stream.read_exact(&mut body).await?;
+            body
+        }
+        None => Vec::new(),
+    };
+
     Ok(Request {
         method,
         path,
         headers,
+        body,
     })
 }
 
@@ -97,8 +139,43 @@ mod tests {
             Request {
                 method: Method::Get,
                 path: "/foo".to_string(),
-                headers: hashmap! { "Host".to_string() => "localhost".to_string() }
+                headers: hashmap! { "Host".to_string() => "localhost".to_string() },
+                body: Vec::new(),
             }
         )
     }
+
+    #[tokio::test]
+    async fn reads_body_per_content_length() {
+        let mut stream = Cursor::new(indoc!(
+            "
+            POST /foo HTTP/1.1\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello"
+        ));
+        let req = parse_request(&mut stream).await.unwrap();
+
+        assert_eq!(
+            req,
+            Request {
+                method: Method::Post,
+                path: "/foo".to_string(),
+                headers: hashmap! { "Content-Length".to_string() => "5".to_string() },
+                body: b"hello".to_vec(),
+            }
+        )
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_body() {
+        let mut stream = Cursor::new(indoc!(
+            "
+            POST /foo HTTP/1.1\r\n\
+            Content-Length: 999999999999\r\n\
+            \r\n"
+        ));
+
+        assert!(parse_request(&mut stream).await.is_err());
+    }
 }
@@ -1,20 +1,28 @@
 use std::{
     collections::HashMap,
     fmt::{Debug, Display, Formatter},
-    io::Cursor,
+    fs::Metadata,
+    io::{self, Cursor, SeekFrom},
     path::Path,
+    time::SystemTime,
 };
 
 use maplit::hashmap;
 use tokio::{
     fs::File,
-    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
 };
 
+use crate::req::Method;
+
 pub struct Response {
     pub status: Status,
     pub headers: HashMap<String, String>,
     pub data: Box<dyn AsyncRead + Unpin + Send>,
+    /// Whether `data` is an unknown-length body that must be framed as HTTP chunks.
+    /// Only set by constructors that actually stream (e.g. `mjpeg_stream`) — bodiless
+    /// responses (304/416/101) must NOT be chunked, per RFC 7230 §3.3.
+    pub chunked: bool,
 }
 
 impl Response {
@@ -30,13 +38,63 @@ impl Response {
     }
 
     pub async fn write<O: AsyncWrite + Unpin>(mut self, stream: &mut O) -> anyhow::Result<()> {
-        let bytes = self.status_and_headers().into_bytes();
+        if self.chunked {
+            self.headers
+                .insert("Transfer-Encoding".to_string(), "chunked".to_string());
+        }
 
+        let bytes = self.status_and_headers().into_bytes();
         stream.write_all(&bytes).await?;
 
-        tokio::io::copy(&mut self.data, stream).await?;
+        if self.chunked {
+            write_chunked(&mut self.data, stream).await
+        } else {
+            tokio::io::copy(&mut self.data, stream).await?;
+            Ok(())
+        }
+    }
+
+    /// Builds an open-ended `multipart/x-mixed-replace` response that streams JPEG
+    /// frames as they arrive on `data`, e.g. from an `mjpeg::FrameBroadcaster`
+    /// subscription. Sent chunked, since its length isn't known upfront.
+    pub fn mjpeg_stream(data: impl AsyncRead + Unpin + Send + 'static) -> Self {
+        let headers = hashmap! {
+            "Content-Type".to_string() => "multipart/x-mixed-replace; boundary=frame".to_string(),
+        };
+
+        Self {
+            status: Status::Ok,
+            headers,
+            data: Box::new(data),
+            chunked: true,
+        }
+    }
+
+    /// Strips the body from an otherwise complete response while keeping its status
+    /// and headers (including `Content-Length`), for `HEAD` requests. Never chunked:
+    /// a `HEAD` response carries no body to frame, even if the `GET` equivalent streams.
+    pub fn without_body(mut self) -> Self {
+        self.data = Box::new(Cursor::new(Vec::new()));
+        self.chunked = false;
+        self.headers.remove("Transfer-Encoding");
+        self
+    }
+
+    /// Builds a `405 Method Not Allowed` response listing the methods a handler does
+    /// support in the `Allow` header.
+    pub fn method_not_allowed(allowed: &[Method]) -> Self {
+        let allow = allowed
+            .iter()
+            .map(|method| method.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
 
-        Ok(())
+        let mut response = Response::from_html(
+            Status::MethodNotAllowed,
+            "<html><body><h1>405 Method Not Allowed</h1></body></html>",
+        );
+        response.headers.insert("Allow".to_string(), allow);
+        response
     }
 
     pub fn from_html(status: Status, data: impl ToString) -> Self {
@@ -51,23 +109,208 @@ impl Response {
             status,
             headers,
             data: Box::new(Cursor::new(bytes)),
+            chunked: false,
         }
     }
 
     pub async fn from_file(path: &Path, file: File) -> anyhow::Result<Response> {
-        let headers = hashmap! {
-            "Content-Length".to_string() => file.metadata().await?.len().to_string(),
+        let metadata = file.metadata().await?;
+
+        let mut headers = hashmap! {
+            "Content-Length".to_string() => metadata.len().to_string(),
             "Content-Type".to_string() => mime_type(path).to_string(),
+            "Accept-Ranges".to_string() => "bytes".to_string(),
         };
+        headers.extend(caching_headers(&metadata)?);
 
         Ok(Response {
             headers,
             status: Status::Ok,
             data: Box::new(file),
+            chunked: false,
+        })
+    }
+
+    /// Builds a `304 Not Modified` response carrying the caching validators and an
+    /// empty body (`Content-Length: 0`), for a request that already holds a fresh cached copy of the file. Never
+    /// chunked: a 304 carries no body, per RFC 7230 §3.3.
+    pub fn not_modified(metadata: &Metadata) -> anyhow::Result<Response> {
+        let mut headers = caching_headers(metadata)?;
+        headers.insert("Content-Length".to_string(), "0".to_string());
+
+        Ok(Response {
+            status: Status::NotModified,
+            headers,
+            data: Box::new(Cursor::new(Vec::new())),
+            chunked: false,
+        })
+    }
+
+    /// Serves a single byte range of `file`, as requested by a `Range` header.
+    ///
+    /// Responds `206 Partial Content` with the requested slice, or `416 Range Not
+    /// Satisfiable` (with a `Content-Range: bytes */<total_len>` header) when `range`
+    /// cannot be satisfied against a file of `total_len` bytes.
+    pub async fn from_file_range(
+        path: &Path,
+        mut file: File,
+        metadata: &Metadata,
+        range: &str,
+    ) -> anyhow::Result<Response> {
+        let total_len = metadata.len();
+
+        let Some(range) = parse_byte_range(range, total_len) else {
+            let headers = hashmap! {
+                "Content-Range".to_string() => format!("bytes */{total_len}"),
+                "Content-Length".to_string() => "0".to_string(),
+            };
+
+            return Ok(Response {
+                status: Status::RangeNotSatisfiable,
+                headers,
+                data: Box::new(Cursor::new(Vec::new())),
+                chunked: false,
+            });
+        };
+
+        file.seek(SeekFrom::Start(range.start)).await?;
+
+        let mut headers = hashmap! {
+            "Content-Range".to_string() => format!("bytes {}-{}/{total_len}", range.start, range.end),
+            "Content-Length".to_string() => range.len().to_string(),
+            "Content-Type".to_string() => mime_type(path).to_string(),
+            "Accept-Ranges".to_string() => "bytes".to_string(),
+        };
+        headers.extend(caching_headers(metadata)?);
+
+        Ok(Response {
+            status: Status::PartialContent,
+            headers,
+            data: Box::new(file.take(range.len())),
+            chunked: false,
         })
     }
 }
 
+/// Computes a weak ETag from a file's size and modification time, e.g. `W/"42-1690000000"`.
+fn compute_etag(metadata: &Metadata) -> io::Result<String> {
+    let mtime_secs = metadata
+        .modified()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    Ok(format!("W/\"{}-{mtime_secs}\"", metadata.len()))
+}
+
+fn caching_headers(metadata: &Metadata) -> anyhow::Result<HashMap<String, String>> {
+    Ok(hashmap! {
+        "ETag".to_string() => compute_etag(metadata)?,
+        "Last-Modified".to_string() => httpdate::fmt_http_date(metadata.modified()?),
+    })
+}
+
+/// Checks whether `request_headers` already hold a fresh copy of the file described by
+/// `metadata`, via `If-None-Match` (against the weak ETag) or `If-Modified-Since`.
+pub fn is_not_modified(
+    request_headers: &HashMap<String, String>,
+    metadata: &Metadata,
+) -> anyhow::Result<bool> {
+    if let Some(etag) = request_headers.get("If-None-Match") {
+        if *etag == compute_etag(metadata)? {
+            return Ok(true);
+        }
+    }
+
+    if let Some(since) = request_headers.get("If-Modified-Since") {
+        if let Ok(since) = httpdate::parse_http_date(since) {
+            // HTTP dates have whole-second precision, so compare the mtime at that
+            // precision too; otherwise the `Last-Modified` value we sent never matches.
+            let to_secs = |time: SystemTime| {
+                time.duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            };
+            if to_secs(metadata.modified()?) <= to_secs(since) {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+struct ByteRange {
+    start: u64,
+    end: u64,
+}
+
+impl ByteRange {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// Parses a `Range: bytes=<start>-<end>` header value, supporting the suffix
+/// (`bytes=-<n>`) and open-ended (`bytes=<start>-`) forms. Returns `None` if the
+/// header is malformed or the range cannot be satisfied for a file of `total_len` bytes.
+fn parse_byte_range(header: &str, total_len: u64) -> Option<ByteRange> {
+    let spec = header.strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+
+    let range = if start.is_empty() {
+        let suffix_len: u64 = end.parse().ok()?;
+        ByteRange {
+            start: total_len.saturating_sub(suffix_len),
+            end: total_len.checked_sub(1)?,
+        }
+    } else if end.is_empty() {
+        ByteRange {
+            start: start.parse().ok()?,
+            end: total_len.checked_sub(1)?,
+        }
+    } else {
+        ByteRange {
+            start: start.parse().ok()?,
+            end: end.parse().ok()?,
+        }
+    };
+
+    if range.start > range.end || range.start >= total_len {
+        return None;
+    }
+
+    Some(ByteRange {
+        start: range.start,
+        end: range.end.min(total_len - 1),
+    })
+}
+
+/// Frames `data` into HTTP chunks (`<hex-len>\r\n<bytes>\r\n`, terminated by `0\r\n\r\n`)
+/// as it's written to `stream`, for responses with `chunked` set (an unknown-length
+/// streaming body).
+async fn write_chunked<R: AsyncRead + Unpin, O: AsyncWrite + Unpin>(
+    data: &mut R,
+    stream: &mut O,
+) -> anyhow::Result<()> {
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let n = data.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+
+        stream.write_all(format!("{n:x}\r\n").as_bytes()).await?;
+        stream.write_all(&buf[..n]).await?;
+        stream.write_all(b"\r\n").await?;
+    }
+
+    stream.write_all(b"0\r\n\r\n").await?;
+
+    Ok(())
+}
+
 fn mime_type(path: &Path) -> &str {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some("html") => "text/html",
@@ -84,6 +327,11 @@ fn mime_type(path: &Path) -> &str {
 pub enum Status {
     NotFound,
     Ok,
+    PartialContent,
+    RangeNotSatisfiable,
+    NotModified,
+    SwitchingProtocols,
+    MethodNotAllowed,
 }
 
 impl Display for Status {
@@ -91,6 +339,11 @@ impl Display for Status {
         match self {
             Status::NotFound => write!(f, "404 Not Found"),
             Status::Ok => write!(f, "200 OK"),
+            Status::PartialContent => write!(f, "206 Partial Content"),
+            Status::RangeNotSatisfiable => write!(f, "416 Range Not Satisfiable"),
+            Status::NotModified => write!(f, "304 Not Modified"),
+            Status::SwitchingProtocols => write!(f, "101 Switching Protocols"),
+            Status::MethodNotAllowed => write!(f, "405 Method Not Allowed"),
         }
     }
 }
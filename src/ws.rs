@@ -0,0 +1,181 @@
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::req::Request;
+use crate::resp::{Response, Status};
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Caps how much memory a single frame's payload can claim via its (client-controlled)
+/// length prefix.
+const MAX_FRAME_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Whether `request` asks to upgrade the connection to the WebSocket protocol.
+pub fn is_upgrade_request(request: &Request) -> bool {
+    header_has_token(request, "Upgrade", "websocket")
+        && header_has_token(request, "Connection", "upgrade")
+}
+
+fn header_has_token(request: &Request, name: &str, token: &str) -> bool {
+    request.headers.get(name).is_some_and(|value| {
+        value
+            .to_lowercase()
+            .split(',')
+            .any(|part| part.trim() == token)
+    })
+}
+
+/// Computes `Sec-WebSocket-Accept` as `base64(sha1(key + ACCEPT_GUID))`, per RFC 6455 §1.3.
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(ACCEPT_GUID.as_bytes());
+
+    base64::engine::general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+/// Builds the `101 Switching Protocols` handshake response for the given `Sec-WebSocket-Key`.
+pub fn handshake_response(key: &str) -> Response {
+    Response {
+        status: Status::SwitchingProtocols,
+        headers: maplit::hashmap! {
+            "Upgrade".to_string() => "websocket".to_string(),
+            "Connection".to_string() => "Upgrade".to_string(),
+            "Sec-WebSocket-Accept".to_string() => accept_key(key),
+            "Content-Length".to_string() => "0".to_string(),
+        },
+        data: Box::new(std::io::Cursor::new(Vec::new())),
+        chunked: false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0x0 => Ok(Opcode::Continuation),
+            0x1 => Ok(Opcode::Text),
+            0x2 => Ok(Opcode::Binary),
+            0x8 => Ok(Opcode::Close),
+            0x9 => Ok(Opcode::Ping),
+            0xA => Ok(Opcode::Pong),
+            other => Err(anyhow::anyhow!("unsupported WebSocket opcode: {other:#x}")),
+        }
+    }
+
+    fn as_byte(self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+        }
+    }
+}
+
+struct Frame {
+    opcode: Opcode,
+    payload: Vec<u8>,
+}
+
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> anyhow::Result<Frame> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await?;
+
+    let opcode = Opcode::from_byte(header[0] & 0x0F)?;
+    let masked = header[1] & 0x80 != 0;
+    let mut len = u64::from(header[1] & 0x7F);
+
+    if len == 126 {
+        let mut ext = [0u8; 2];
+        stream.read_exact(&mut ext).await?;
+        len = u64::from(u16::from_be_bytes(ext));
+    } else if len == 127 {
+        let mut ext = [0u8; 8];
+        stream.read_exact(&mut ext).await?;
+        len = u64::from_be_bytes(ext);
+    }
+
+    if len > MAX_FRAME_SIZE {
+        return Err(anyhow::anyhow!("frame payload too large: {len} bytes"));
+    }
+
+    // Clients must mask every frame they send (RFC 6455 §5.1); servers never do.
+    let mut mask_key = [0u8; 4];
+    if masked {
+        stream.read_exact(&mut mask_key).await?;
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload).await?;
+
+    if masked {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask_key[i % 4];
+        }
+    }
+
+    Ok(Frame { opcode, payload })
+}
+
+async fn write_frame<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    opcode: Opcode,
+    payload: &[u8],
+) -> anyhow::Result<()> {
+    let mut header = vec![0x80 | opcode.as_byte()];
+
+    let len = payload.len();
+    if len < 126 {
+        header.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        header.push(126);
+        header.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        header.push(127);
+        header.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+
+    stream.write_all(&header).await?;
+    stream.write_all(payload).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+/// Echoes every text/binary frame back to the client, replies to pings with pongs,
+/// and closes the connection once a close frame is received or framing breaks down.
+pub async fn echo<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S) -> anyhow::Result<()> {
+    loop {
+        let frame = match read_frame(stream).await {
+            Ok(frame) => frame,
+            Err(_) => break,
+        };
+
+        match frame.opcode {
+            Opcode::Text | Opcode::Binary => {
+                write_frame(stream, frame.opcode, &frame.payload).await?;
+            }
+            Opcode::Ping => write_frame(stream, Opcode::Pong, &frame.payload).await?,
+            Opcode::Pong | Opcode::Continuation => {}
+            Opcode::Close => {
+                write_frame(stream, Opcode::Close, &frame.payload).await?;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}